@@ -5,32 +5,57 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-use iced::widget::{button, column, container, horizontal_space, row, text, text_editor};
-use iced::{Element, Font, Length, Task};
-use rfd::{AsyncFileDialog, FileHandle};
+use iced::futures::{SinkExt, Stream};
+use iced::highlighter::{self, Highlighter};
+use iced::keyboard;
+use iced::widget::{
+    button, column, container, horizontal_space, pick_list, row, text, text_editor, tooltip,
+};
+use iced::{stream, Element, Font, Length, Subscription, Task, Theme};
+use notify::Watcher;
+use rfd::{AsyncFileDialog, FileHandle, MessageButtons, MessageDialogResult, MessageLevel};
 
-#[derive(Debug, Default)]
-struct State {
+/// A stable identity for a [`Buffer`], distinct from its position in
+/// `State::buffers`. Tabs can be reordered or closed while a `Task` is in
+/// flight, so anything that needs to find its way back to a specific
+/// buffer after an `await` must go through this instead of a raw index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BufferId(u64);
+
+/// The state of a single open file: its text, where it lives on disk (if
+/// anywhere), and whether it has unsaved edits.
+#[derive(Debug)]
+struct Buffer {
+    id: BufferId,
     content: text_editor::Content,
     file_path: Option<PathBuf>,
     prev_path: PathBuf,
+    modified: bool,
+    /// Set once a watcher reports the open file changed on disk while the
+    /// buffer was dirty, so `view_bottom_info` can offer a reload button.
+    external_change: bool,
+    /// Set right after this buffer's own `Save`/`SaveAs` writes its file,
+    /// so the filesystem event that write itself triggers isn't mistaken
+    /// for an external change. Cleared by the next watcher event, whether
+    /// or not it turns out to be the self-triggered one.
+    just_saved: bool,
     error: VecDeque<String>,
 }
 
-#[derive(Debug, Clone)]
-enum Message {
-    Edit(text_editor::Action),
-    FileOpened(Arc<io::Result<String>>, PathBuf),
-    OpenFileDialog,
-    OpenFile(Option<FileHandle>),
-    RemoveError,
-    New,
-    Save,
-    SaveAs,
-    SavedFile(Option<Arc<io::Result<PathBuf>>>),
-}
+impl Buffer {
+    fn new(id: BufferId) -> Self {
+        Self {
+            id,
+            content: text_editor::Content::new(),
+            file_path: None,
+            prev_path: PathBuf::new(),
+            modified: false,
+            external_change: false,
+            just_saved: false,
+            error: VecDeque::new(),
+        }
+    }
 
-impl State {
     fn set_file_path(&mut self, path: Option<PathBuf>) {
         match path.clone() {
             Some(p) => {
@@ -46,142 +71,501 @@ impl State {
         };
         self.file_path = path
     }
+
+    /// The syntax token passed to the highlighter, derived from the open
+    /// file's extension. Falls back to plain text for new buffers or
+    /// files without an extension.
+    fn highlight_token(&self) -> String {
+        self.file_path
+            .as_deref()
+            .and_then(Path::extension)
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("txt")
+            .to_string()
+    }
+
+    /// The label shown on this buffer's tab.
+    fn title(&self) -> String {
+        let name = self
+            .file_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or("untitled");
+
+        if self.modified {
+            format!("{name}*")
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    buffers: Vec<Buffer>,
+    active: usize,
+    next_buffer_id: u64,
+    pending_action: Option<PendingAction>,
+    theme: Theme,
+    highlighter_theme: highlighter::Theme,
+}
+
+/// An action that was interrupted by the discard-confirmation dialog and
+/// should be resumed once the user has decided what to do with the
+/// unsaved changes. Targets are captured by [`BufferId`] rather than
+/// position, since tabs can be closed or reordered while the dialog (or a
+/// save it triggered) is still in flight.
+#[derive(Debug, Clone, Copy)]
+enum PendingAction {
+    OpenFileDialog,
+    CloseTab(BufferId),
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Edit(text_editor::Action),
+    FileOpened(Arc<io::Result<String>>, PathBuf, BufferId),
+    OpenFileDialog,
+    OpenFile(Option<FileHandle>, BufferId),
+    RemoveError(BufferId),
+    New,
+    Save,
+    SaveAs,
+    SavedFile(Option<Arc<io::Result<PathBuf>>>),
+    ConfirmDiscard(PendingAction),
+    DiscardChoice(MessageDialogResult),
+    FileChangedOnDisk(BufferId),
+    Reload(BufferId),
+    SelectTab(usize),
+    CloseTab(usize),
+    SetTheme(Theme),
 }
 
 impl State {
     fn new() -> (Self, Task<Message>) {
+        let theme = load_theme();
+        let highlighter_theme = highlighter_theme_for(&theme);
+
         (
             Self {
-                content: text_editor::Content::new(),
-                file_path: None,
-                prev_path: PathBuf::new(),
-                error: VecDeque::new(),
+                buffers: vec![Buffer::new(BufferId(0))],
+                active: 0,
+                next_buffer_id: 1,
+                pending_action: None,
+                theme,
+                highlighter_theme,
             },
             Task::none(),
         )
     }
 
+    fn theme(&self) -> Theme {
+        self.theme.clone()
+    }
+
+    fn active_buffer(&self) -> &Buffer {
+        &self.buffers[self.active]
+    }
+
+    fn active_buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.active]
+    }
+
+    fn alloc_buffer_id(&mut self) -> BufferId {
+        let id = BufferId(self.next_buffer_id);
+        self.next_buffer_id += 1;
+        id
+    }
+
+    /// The current position of a [`PendingAction`]'s target buffer, or
+    /// `None` if that buffer was closed while the action was pending.
+    fn pending_index(&self, action: PendingAction) -> Option<usize> {
+        match action {
+            PendingAction::OpenFileDialog => Some(self.active),
+            PendingAction::CloseTab(id) => self.buffers.iter().position(|buffer| buffer.id == id),
+        }
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Edit(action) => {
-                self.content.perform(action);
+                if let text_editor::Action::Edit(_) = action {
+                    self.active_buffer_mut().modified = true;
+                }
+                self.active_buffer_mut().content.perform(action);
                 Task::none()
             }
-            Message::FileOpened(result, path) => match &*result {
-                Ok(text) => {
-                    self.content = text_editor::Content::with_text(text);
-                    self.set_file_path(Some(path));
-                    Task::none()
+            Message::FileOpened(result, path, id) => {
+                let Some(buffer) = self.buffers.iter_mut().find(|buffer| buffer.id == id) else {
+                    // The tab this load was meant for was closed while the
+                    // file was being read.
+                    return Task::none();
+                };
+                match &*result {
+                    Ok(text) => {
+                        buffer.content = text_editor::Content::with_text(text);
+                        buffer.set_file_path(Some(path));
+                        buffer.modified = false;
+                        buffer.external_change = false;
+                        Task::none()
+                    }
+                    Err(error) => self.add_error(id, format!("Could not open file: {error}")),
                 }
-                Err(error) => self.add_error(format!("Could not open file: {error}")),
-            },
-            Message::OpenFileDialog => Task::perform(
-                file_select_win_builder(
-                    "Open file ...",
-                    self.prev_path.clone(),
-                    file_name_opt(self.file_path.as_ref()),
-                )
-                .pick_file(),
-                Message::OpenFile,
-            ),
-            Message::OpenFile(file_handle_opt) => match file_handle_opt {
+            }
+            Message::OpenFileDialog => {
+                if self.active_buffer().modified {
+                    Task::done(Message::ConfirmDiscard(PendingAction::OpenFileDialog))
+                } else {
+                    self.open_file_dialog_task()
+                }
+            }
+            Message::OpenFile(file_handle_opt, id) => match file_handle_opt {
                 Some(handle) => {
-                    Task::perform(load_file(handle.path().to_path_buf()), |(res, buf)| {
-                        Message::FileOpened(res, buf)
+                    Task::perform(load_file(handle.path().to_path_buf()), move |(res, buf)| {
+                        Message::FileOpened(res, buf, id)
                     })
                 }
                 None => Task::none(),
             },
-            Message::RemoveError => {
-                self.error.pop_front();
+            Message::RemoveError(id) => {
+                if let Some(buffer) = self.buffers.iter_mut().find(|buffer| buffer.id == id) {
+                    buffer.error.pop_front();
+                }
                 Task::none()
             }
             Message::New => {
-                self.content = text_editor::Content::new();
-                self.set_file_path(None);
+                let id = self.alloc_buffer_id();
+                self.buffers.push(Buffer::new(id));
+                self.active = self.buffers.len() - 1;
                 Task::none()
             }
             Message::Save => {
-                let text = self.content.text();
+                let buffer = self.active_buffer();
+                let text = buffer.content.text();
                 Task::perform(
-                    save_file(self.file_path.clone(), self.prev_path.clone(), text),
+                    save_file(buffer.file_path.clone(), buffer.prev_path.clone(), text),
                     Message::SavedFile,
                 )
             }
             Message::SaveAs => {
-                let text = self.content.text();
+                let buffer = self.active_buffer();
+                let text = buffer.content.text();
                 Task::perform(
                     save_file_as(
-                        self.prev_path.clone(),
+                        buffer.prev_path.clone(),
                         text,
-                        file_name_opt(self.file_path.as_ref()),
+                        file_name_opt(buffer.file_path.as_ref()),
                     ),
                     Message::SavedFile,
                 )
             }
-            Message::SavedFile(data) => match data {
-                Some(result) => match &*result {
-                    Ok(path) => {
-                        self.set_file_path(Some(path.clone()));
-                        self.add_error(format!("{} saved!", path.to_str().unwrap_or("file")))
+            Message::SavedFile(data) => {
+                let pending = self.pending_action.take();
+                let target = match pending {
+                    Some(action) => match self.pending_index(action) {
+                        Some(index) => index,
+                        // The pending action's tab was closed while the
+                        // save was in flight; there's nothing left to
+                        // apply the result to.
+                        None => return Task::none(),
+                    },
+                    None => self.active,
+                };
+
+                let saved = match data {
+                    Some(result) => match &*result {
+                        Ok(path) => {
+                            let id = self.buffers[target].id;
+                            let buffer = &mut self.buffers[target];
+                            buffer.set_file_path(Some(path.clone()));
+                            buffer.modified = false;
+                            buffer.just_saved = true;
+                            Some(self.add_error(
+                                id,
+                                format!("{} saved!", path.to_str().unwrap_or("file")),
+                            ))
+                        }
+                        Err(error) => {
+                            let id = self.buffers[target].id;
+                            Some(self.add_error(id, format!("Could not save file: {error}")))
+                        }
+                    },
+                    None => {
+                        let id = self.buffers[target].id;
+                        Some(self.add_error(id, "File save aborted. File not saved.".to_string()))
                     }
-                    Err(error) => self.add_error(format!("Could not save file: {error}")),
-                },
-                None => self.add_error("File save aborted. File not saved.".to_string()),
-            },
+                };
+
+                match pending {
+                    Some(action) if !self.buffers[target].modified => {
+                        saved.unwrap_or(Task::none()).chain(self.resume(action))
+                    }
+                    _ => saved.unwrap_or(Task::none()),
+                }
+            }
+            Message::ConfirmDiscard(action) => {
+                self.pending_action = Some(action);
+                Task::perform(confirm_discard_dialog(), Message::DiscardChoice)
+            }
+            Message::DiscardChoice(choice) => {
+                let Some(action) = self.pending_action.take() else {
+                    return Task::none();
+                };
+                match choice {
+                    MessageDialogResult::Yes => {
+                        let Some(index) = self.pending_index(action) else {
+                            // The target tab closed while the dialog was
+                            // open; nothing left to save.
+                            return Task::none();
+                        };
+                        self.pending_action = Some(action);
+                        let buffer = &self.buffers[index];
+                        let text = buffer.content.text();
+                        Task::perform(
+                            save_file(buffer.file_path.clone(), buffer.prev_path.clone(), text),
+                            Message::SavedFile,
+                        )
+                    }
+                    MessageDialogResult::No => self.resume(action),
+                    _ => Task::none(),
+                }
+            }
+            Message::FileChangedOnDisk(id) => {
+                let Some(buffer) = self.buffers.iter_mut().find(|buffer| buffer.id == id) else {
+                    // The tab this event was about has since been closed.
+                    return Task::none();
+                };
+                if buffer.just_saved {
+                    buffer.just_saved = false;
+                    return Task::none();
+                }
+                if buffer.modified {
+                    buffer.external_change = true;
+                    self.add_error(id, "File changed on disk — press reload".to_string())
+                } else {
+                    self.reload_task(id)
+                }
+            }
+            Message::Reload(id) => self.reload_task(id),
+            Message::SelectTab(index) => {
+                if index < self.buffers.len() {
+                    self.active = index;
+                }
+                Task::none()
+            }
+            Message::CloseTab(index) => {
+                if index >= self.buffers.len() {
+                    Task::none()
+                } else if self.buffers[index].modified {
+                    let id = self.buffers[index].id;
+                    Task::done(Message::ConfirmDiscard(PendingAction::CloseTab(id)))
+                } else {
+                    self.close_tab(index);
+                    Task::none()
+                }
+            }
+            Message::SetTheme(theme) => {
+                self.highlighter_theme = highlighter_theme_for(&theme);
+                self.theme = theme;
+                save_theme(&self.theme);
+                Task::none()
+            }
+        }
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        if index >= self.buffers.len() {
+            return;
+        }
+
+        self.buffers.remove(index);
+        if self.buffers.is_empty() {
+            let id = self.alloc_buffer_id();
+            self.buffers.push(Buffer::new(id));
+        }
+
+        if index < self.active {
+            self.active -= 1;
+        }
+        if self.active >= self.buffers.len() {
+            self.active = self.buffers.len() - 1;
+        }
+    }
+
+    /// Reloads the buffer identified by `id` from its file, if it still has
+    /// one open. A no-op if that tab has since been closed.
+    fn reload_task(&self, id: BufferId) -> Task<Message> {
+        let path = self
+            .buffers
+            .iter()
+            .find(|buffer| buffer.id == id)
+            .and_then(|buffer| buffer.file_path.clone());
+
+        match path {
+            Some(path) => Task::perform(load_file(path), move |(res, buf)| {
+                Message::FileOpened(res, buf, id)
+            }),
+            None => Task::none(),
+        }
+    }
+
+    /// Arms a filesystem watcher on every open buffer's file so external
+    /// edits surface as `Message::FileChangedOnDisk`, tagged with the
+    /// `BufferId` they belong to. Each watcher is keyed on its buffer's id
+    /// and file path, so closing a tab drops its watcher without disturbing
+    /// the rest, pointing a tab at a different file re-arms it on the new
+    /// path, and an in-flight event can't land on the wrong (now active)
+    /// buffer. Also listens for the toolbar's keyboard shortcuts.
+    fn subscription(&self) -> Subscription<Message> {
+        let mut subscriptions: Vec<_> = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| {
+                let path = buffer.file_path.clone()?;
+                Some(Subscription::run_with_id(
+                    (buffer.id, path.clone()),
+                    watch_file(buffer.id, path),
+                ))
+            })
+            .collect();
+        subscriptions.push(keyboard_shortcuts());
+
+        Subscription::batch(subscriptions)
+    }
+
+    fn open_file_dialog_task(&self) -> Task<Message> {
+        let buffer = self.active_buffer();
+        let id = buffer.id;
+        Task::perform(
+            file_select_win_builder(
+                "Open file ...",
+                buffer.prev_path.clone(),
+                file_name_opt(buffer.file_path.as_ref()),
+            )
+            .pick_file(),
+            move |handle| Message::OpenFile(handle, id),
+        )
+    }
+
+    /// Carries out a [`PendingAction`] that was deferred behind the
+    /// discard-confirmation dialog, bypassing the dirty check since the
+    /// user has already resolved it.
+    fn resume(&mut self, action: PendingAction) -> Task<Message> {
+        match action {
+            PendingAction::OpenFileDialog => self.open_file_dialog_task(),
+            PendingAction::CloseTab(id) => {
+                if let Some(index) = self.buffers.iter().position(|buffer| buffer.id == id) {
+                    self.close_tab(index);
+                }
+                Task::none()
+            }
         }
     }
 
     fn view(&self) -> Element<'_, Message> {
+        let tabs = self.view_tabs();
         let menu = self.view_menu();
 
-        let placeholder = match self.file_path {
+        let buffer = self.active_buffer();
+        let placeholder = match buffer.file_path {
             Some(_) => "Type here ...",
             None => "Welcome! Open a file or start typing here ...",
         };
-        let editor = text_editor(&self.content)
+        let editor = text_editor(&buffer.content)
             .placeholder(placeholder)
             .on_action(Message::Edit)
+            .highlight::<Highlighter>(
+                highlighter::Settings {
+                    theme: self.highlighter_theme,
+                    token: buffer.highlight_token(),
+                },
+                |highlight, _theme| highlighter::Format {
+                    color: highlight.color(),
+                    font: None,
+                },
+            )
             .height(Length::Fill);
 
         let bottom_info = self.view_bottom_info();
 
-        container(column![menu, editor, bottom_info].spacing(10))
+        container(column![tabs, menu, editor, bottom_info].spacing(10))
             .padding(10)
             .into()
     }
 
+    fn view_tabs(&self) -> Element<'_, Message> {
+        let tabs = self.buffers.iter().enumerate().map(|(index, buffer)| {
+            let label = button(text(buffer.title())).on_press(Message::SelectTab(index));
+            let close = button(text("x")).on_press(Message::CloseTab(index));
+
+            row![label, close].spacing(4).into()
+        });
+
+        row(tabs).spacing(10).into()
+    }
+
     fn view_menu(&self) -> Element<'_, Message> {
-        let new_button = button(icon(Icon::NewFile)).on_press(Message::New);
-        let open_button = button(icon(Icon::File)).on_press(Message::OpenFileDialog);
-        let save_button = button(icon(Icon::Save)).on_press(Message::Save);
-        //let save_as_button = button("save as").on_press(Message::SaveAs);
+        let new_button = toolbar_button(icon(Icon::NewFile), Message::New, "New (Ctrl+N)");
+        let open_button = toolbar_button(icon(Icon::File), Message::OpenFileDialog, "Open (Ctrl+O)");
+        let save_button = toolbar_button(icon(Icon::Save), Message::Save, "Save (Ctrl+S)");
+        let save_as_button = toolbar_button(
+            icon(Icon::SaveAs),
+            Message::SaveAs,
+            "Save As (Ctrl+Shift+S)",
+        );
 
-        row![new_button, open_button, save_button/*, save_as_button*/]
-            .spacing(10)
-            .into()
+        let theme_picker = pick_list(Theme::ALL, Some(self.theme.clone()), Message::SetTheme);
+
+        row![
+            new_button,
+            open_button,
+            save_button,
+            save_as_button,
+            horizontal_space(),
+            theme_picker
+        ]
+        .spacing(10)
+        .into()
     }
 
     fn view_bottom_info(&self) -> Element<'_, Message> {
-        let (line, column) = self.content.cursor_position();
+        let buffer = self.active_buffer();
+        let (line, column) = buffer.content.cursor_position();
         let cursor_position = text(format!("Line: {}, Column: {}", line + 1, column + 1));
 
-        let path = self
+        let path = buffer
             .file_path
             .as_deref()
             .map(Path::to_str)
             .unwrap_or(Some("No file yet, please save or open file :)"))
             .unwrap_or("Can't display path :(");
+        let path = if buffer.modified {
+            format!("{path}*")
+        } else {
+            path.to_string()
+        };
 
-        let error = self.error.front().map(String::as_str).unwrap_or(path);
+        let error = buffer.error.front().map(String::as_str).unwrap_or(&path);
 
-        row![text(error), horizontal_space(), cursor_position].into()
+        let mut info = row![text(error), horizontal_space()];
+        if buffer.external_change {
+            info = info.push(button("Reload").on_press(Message::Reload(buffer.id)));
+        }
+
+        info.push(cursor_position).into()
     }
 
-    fn add_error(&mut self, text: String) -> Task<Message> {
-        self.error.push_back(text);
-        Task::perform(tokio::time::sleep(Duration::from_secs(4)), |_| {
-            Message::RemoveError
+    /// Queues a transient notice on the buffer identified by `id`, cleared
+    /// after a few seconds. Keyed by [`BufferId`] so the removal still
+    /// lands on the right tab even if tabs are reordered or closed while
+    /// the timer is running.
+    fn add_error(&mut self, id: BufferId, text: String) -> Task<Message> {
+        if let Some(buffer) = self.buffers.iter_mut().find(|buffer| buffer.id == id) {
+            buffer.error.push_back(text);
+        }
+        Task::perform(tokio::time::sleep(Duration::from_secs(4)), move |_| {
+            Message::RemoveError(id)
         })
     }
 }
@@ -190,6 +574,75 @@ async fn load_file(path: impl AsRef<Path>) -> (Arc<io::Result<String>>, PathBuf)
     (Arc::new(tokio::fs::read_to_string(path).await), buf)
 }
 
+/// Watches `path` for modify (including rename) and remove events and yields a `FileChangedOnDisk`
+/// message tagged with `id` for each one, including the ones the app's own
+/// `Save`/`SaveAs` just caused — `Buffer::just_saved` is what tells those
+/// apart on the receiving end. `notify`'s callback runs on its own thread,
+/// so events are forwarded through a blocking channel and relayed into the
+/// async stream via `spawn_blocking`.
+fn watch_file(id: BufferId, path: PathBuf) -> impl Stream<Item = Message> {
+    stream::channel(10, move |mut output| async move {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        }) {
+            Ok(watcher) => watcher,
+            Err(_) => return,
+        };
+
+        if watcher
+            .watch(&path, notify::RecursiveMode::NonRecursive)
+            .is_err()
+        {
+            return;
+        }
+
+        let mut rx = rx;
+        loop {
+            let (event, returned_rx) =
+                match tokio::task::spawn_blocking(move || (rx.recv(), rx)).await {
+                    Ok(pair) => pair,
+                    Err(_) => break,
+                };
+            rx = returned_rx;
+
+            match event {
+                Ok(Ok(event)) => {
+                    if matches!(
+                        event.kind,
+                        notify::EventKind::Modify(_) | notify::EventKind::Remove(_)
+                    ) && output.send(Message::FileChangedOnDisk(id)).await.is_err()
+                    {
+                        break;
+                    }
+                }
+                Ok(Err(_)) | Err(_) => break,
+            }
+        }
+    })
+}
+
+/// Maps the toolbar's keyboard shortcuts: Ctrl+N, Ctrl+O, Ctrl+S and
+/// Ctrl+Shift+S (Cmd on macOS, via `Modifiers::command`).
+fn keyboard_shortcuts() -> Subscription<Message> {
+    keyboard::on_key_press(|key, modifiers| {
+        if !modifiers.command() {
+            return None;
+        }
+
+        match key.as_ref() {
+            // Shift changes the logical key's glyph ("s" -> "S"), so both
+            // must be matched here for Ctrl+Shift+S to be reachable at all.
+            keyboard::Key::Character("s" | "S") if modifiers.shift() => Some(Message::SaveAs),
+            keyboard::Key::Character("s") => Some(Message::Save),
+            keyboard::Key::Character("n") => Some(Message::New),
+            keyboard::Key::Character("o") => Some(Message::OpenFileDialog),
+            _ => None,
+        }
+    })
+}
+
 async fn save_file(
     file_path: Option<impl AsRef<Path>>,
     root_search_path: impl AsRef<Path>,
@@ -220,6 +673,59 @@ async fn save_file_as(
     }
 }
 
+/// The syntect highlighting theme that best matches an app `Theme`, so
+/// code coloring stays readable whichever palette is selected.
+fn highlighter_theme_for(theme: &Theme) -> highlighter::Theme {
+    match theme {
+        Theme::Light
+        | Theme::SolarizedLight
+        | Theme::GruvboxLight
+        | Theme::CatppuccinLatte
+        | Theme::TokyoNightLight
+        | Theme::KanagawaLotus => highlighter::Theme::InspiredGitHub,
+        _ => highlighter::Theme::SolarizedDark,
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let mut path = dirs::config_dir()?;
+    path.push("first-iced-app");
+    path.push("theme.txt");
+    Some(path)
+}
+
+fn load_theme() -> Theme {
+    config_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|saved| {
+            Theme::ALL
+                .iter()
+                .find(|theme| theme.to_string() == saved.trim())
+                .cloned()
+        })
+        .unwrap_or_default()
+}
+
+fn save_theme(theme: &Theme) {
+    let Some(path) = config_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(path, theme.to_string());
+}
+
+async fn confirm_discard_dialog() -> MessageDialogResult {
+    rfd::AsyncMessageDialog::new()
+        .set_title("Unsaved changes")
+        .set_description("This buffer has unsaved changes. Save them before continuing?")
+        .set_level(MessageLevel::Warning)
+        .set_buttons(MessageButtons::YesNoCancel)
+        .show()
+        .await
+}
+
 fn file_select_win_builder(
     title: impl Into<String>,
     path: impl AsRef<Path>,
@@ -257,7 +763,8 @@ fn file_name_opt(path: Option<impl AsRef<Path>>) -> Option<String> {
 enum Icon {
     File,
     NewFile,
-    Save
+    Save,
+    SaveAs,
 }
 
 fn icon<'a>(icon: Icon) -> Element<'a, Message> {
@@ -266,12 +773,28 @@ fn icon<'a>(icon: Icon) -> Element<'a, Message> {
     let code = match icon {
         Icon::File => "\u{E802}",
         Icon::NewFile => "\u{E803}",
-        Icon::Save => "\u{E801}"
+        Icon::Save => "\u{E801}",
+        Icon::SaveAs => "\u{E804}",
     };
 
     text(code).font(FONT).into()
 }
 
+/// Wraps a toolbar icon button in a tooltip naming the action and its
+/// keybinding, shown as a floating box below the button.
+fn toolbar_button<'a>(
+    content: Element<'a, Message>,
+    message: Message,
+    label: &'a str,
+) -> Element<'a, Message> {
+    tooltip(
+        button(content).on_press(message),
+        container(text(label)).padding(5).style(container::rounded_box),
+        tooltip::Position::Bottom,
+    )
+    .into()
+}
+
 fn main() -> iced::Result {
     let mut icon_font_path = PathBuf::new();
     icon_font_path.push("fonts");
@@ -279,5 +802,67 @@ fn main() -> iced::Result {
 
     iced::application("first-app", State::update, State::view)
         .font(std::fs::read(icon_font_path).unwrap())
+        .subscription(State::subscription)
+        .theme(State::theme)
         .run_with(State::new)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_buffer(state: &mut State) -> BufferId {
+        let id = state.alloc_buffer_id();
+        state.buffers.push(Buffer::new(id));
+        id
+    }
+
+    #[test]
+    fn close_tab_shifts_active_index_past_closed_tab() {
+        let (mut state, _) = State::new();
+        let tab_b = push_buffer(&mut state);
+        state.active = 1;
+
+        state.close_tab(0);
+
+        assert_eq!(state.buffers.len(), 1);
+        assert_eq!(state.active, 0);
+        assert_eq!(state.buffers[0].id, tab_b);
+    }
+
+    #[test]
+    fn close_tab_clamps_active_index_when_closing_last_tab() {
+        let (mut state, _) = State::new();
+        push_buffer(&mut state);
+        state.active = 1;
+
+        state.close_tab(1);
+
+        assert_eq!(state.buffers.len(), 1);
+        assert_eq!(state.active, 0);
+    }
+
+    #[test]
+    fn pending_index_follows_target_buffer_past_an_earlier_close() {
+        let (mut state, _) = State::new();
+        let tab_b = push_buffer(&mut state);
+        let pending = PendingAction::CloseTab(tab_b);
+        assert_eq!(state.pending_index(pending), Some(1));
+
+        // Some other tab closes first, shifting B down a slot.
+        state.close_tab(0);
+
+        assert_eq!(state.pending_index(pending), Some(0));
+    }
+
+    #[test]
+    fn pending_index_is_none_once_its_own_tab_is_closed() {
+        let (mut state, _) = State::new();
+        let tab_b = push_buffer(&mut state);
+        let pending = PendingAction::CloseTab(tab_b);
+
+        state.close_tab(1);
+
+        assert_eq!(state.pending_index(pending), None);
+    }
+}